@@ -31,14 +31,147 @@ pub mod email;
 use email::send_password_mail;
 use rand::Rng;
 
-#[derive(Serialize, Deserialize, Debug)]
+pub mod reset_request;
+use reset_request::{save_requests_to_file, ResetRequest, REQUESTS};
+
+pub mod auth_backend;
+use auth_backend::configured_backends;
+
+pub mod totp;
+use totp::{generate_secret, to_base32};
+
+pub mod password_policy;
+
+#[derive(Serialize, Debug)]
 pub struct User {
     pub id: String,
     pub email: String,
     pub name: String,
-    #[serde(with = "BigArray")]
-    pub pw_hash: [u8; 128],
+    pub credentials: Vec<Credential>,
     pub grades: Vec<f32>,
+    #[serde(default)]
+    pub password_hint: Option<String>,
+}
+
+/// Accepts both the current shape and the pre-credential-model shape (a
+/// single `pw_hash: [u8; 128]` field, no `credentials`), synthesizing a
+/// `"password"` credential from `pw_hash` when `credentials` is absent so
+/// that loading an old `936DA01F9ABD4d9d80C702AF85C822A8.txt` doesn't drop
+/// every existing account.
+impl<'de> Deserialize<'de> for User {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct OnDiskUser {
+            id: String,
+            email: String,
+            name: String,
+            #[serde(default)]
+            credentials: Vec<Credential>,
+            #[serde(default)]
+            pw_hash: Option<Vec<u8>>,
+            grades: Vec<f32>,
+            #[serde(default)]
+            password_hint: Option<String>,
+        }
+
+        let raw = OnDiskUser::deserialize(deserializer)?;
+        let mut credentials = raw.credentials;
+        if credentials.is_empty() {
+            if let Some(pw_hash) = raw.pw_hash {
+                let mut padded = [0u8; 128];
+                let len = pw_hash.len().min(padded.len());
+                padded[..len].copy_from_slice(&pw_hash[..len]);
+                let now = unix_now();
+                credentials.push(Credential {
+                    credential_type: String::from(PASSWORD_CREDENTIAL),
+                    credential: padded,
+                    validated: true,
+                    time_created: now,
+                    last_updated: now,
+                });
+            }
+        }
+
+        Ok(User {
+            id: raw.id,
+            email: raw.email,
+            name: raw.name,
+            credentials,
+            grades: raw.grades,
+            password_hint: raw.password_hint,
+        })
+    }
+}
+
+/// A single typed, timestamped secret held by a `User` (password, and later
+/// second factors such as TOTP or a validated-email token), keyed by
+/// `credential_type` so a user can hold more than one at a time.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Credential {
+    pub credential_type: String,
+    #[serde(with = "BigArray")]
+    pub credential: [u8; 128],
+    pub validated: bool,
+    pub time_created: u64,
+    pub last_updated: u64,
+}
+
+pub(crate) const PASSWORD_CREDENTIAL: &str = "password";
+pub(crate) const TOTP_CREDENTIAL: &str = "totp";
+
+/// Inserts or replaces the credential of `credential_type` on `user`,
+/// stamping `last_updated` (and `time_created` on first creation).
+pub fn add_credential(user: &mut User, credential_type: &str, secret: [u8; 128]) {
+    let now = unix_now();
+    match user
+        .credentials
+        .iter_mut()
+        .find(|c| c.credential_type.eq(credential_type))
+    {
+        Some(existing) => {
+            existing.credential = secret;
+            existing.last_updated = now;
+        }
+        None => user.credentials.push(Credential {
+            credential_type: String::from(credential_type),
+            credential: secret,
+            validated: true,
+            time_created: now,
+            last_updated: now,
+        }),
+    }
+}
+
+pub fn credential_of<'a>(user: &'a User, credential_type: &str) -> Option<&'a Credential> {
+    user.credentials
+        .iter()
+        .find(|c| c.credential_type.eq(credential_type))
+}
+
+/// Keeps asking for a password until it satisfies `password_policy::check`,
+/// logging a `warn!` each time a weak one is rejected so administrators can
+/// see where users run into friction.
+fn ask_for_strong_pw(confirm: bool) -> String {
+    loop {
+        let pw = ask_for_pw(confirm);
+        match password_policy::check(&pw) {
+            Ok(()) => return pw,
+            Err(reason) => {
+                println!("{}", reason);
+                warn!("Rejected a weak password: {}", reason);
+            }
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
 
 #[derive(Debug)]
@@ -49,7 +182,22 @@ pub struct UserDTO {
 
 lazy_static! {
     static ref DATABASE: Mutex<Vec<User>> = {
-        let data = read_database_from_file(DATABASE_FILE).unwrap_or(Vec::new());
+        let data = match read_database_from_file(DATABASE_FILE) {
+            Ok(data) => data,
+            Err(err) => {
+                if err
+                    .downcast_ref::<std::io::Error>()
+                    .map_or(false, |io_err| io_err.kind() == std::io::ErrorKind::NotFound)
+                {
+                    Vec::new()
+                } else {
+                    panic!(
+                        "Failed to read {}: {}. Refusing to start and silently wipe an unreadable database.",
+                        DATABASE_FILE, err
+                    );
+                }
+            }
+        };
         Mutex::new(data)
     };
 }
@@ -73,27 +221,13 @@ pub fn login() -> Option<UserDTO> {
 
     let pw = input::<String>().msg("Please enter your password:\n").get();
 
-    if email.eq("admin") {
-        if verify(ADMIN_HASH, &pw) {
-            return Some(UserDTO {
-                email: String::from("admin"),
-                id: String::from("admin"),
-            });
-        }
-    } else {
-        let data = DATABASE.lock().unwrap();
-        for i in 0..(data.len()) {
-            let user = &data[i];
-            if user.email == email && verify(user.pw_hash, &pw) {
-                return Some(UserDTO {
-                    email: String::from(&user.email),
-                    id: String::from(&user.id),
-                });
-            }
+    for backend in configured_backends() {
+        if let Some(user) = backend.authenticate(&email, &pw) {
+            return Some(user);
         }
     }
 
-    return None;
+    None
 }
 
 pub fn create_account(user: &UserDTO, is_teacher_account: bool) {
@@ -113,18 +247,33 @@ pub fn create_account(user: &UserDTO, is_teacher_account: bool) {
 
     //ask for info
     let email = &ask_for_email(false);
-    let pw = ask_for_pw(false);
+    let pw = ask_for_strong_pw(false);
     let name = ask_for_name();
     let id = &Uuid::new_v4().to_string();
 
+    println!("Optionally set a password hint to help you recall it later (leave blank for none):");
+    let hint: String = input().get();
+    let password_hint = if hint.trim().is_empty() {
+        None
+    } else {
+        Some(hint)
+    };
+
     //save in database
     let mut data = DATABASE.lock().unwrap();
     data.push(User {
         id: String::from(id),
         email: String::from(email),
         name: name,
-        pw_hash: padded_hash(&pw),
+        credentials: vec![Credential {
+            credential_type: String::from(PASSWORD_CREDENTIAL),
+            credential: padded_hash(&pw),
+            validated: true,
+            time_created: unix_now(),
+            last_updated: unix_now(),
+        }],
         grades: Vec::new(),
+        password_hint,
     });
 
     //Write into access_control.csv a new teacher with access
@@ -149,6 +298,108 @@ pub fn create_account(user: &UserDTO, is_teacher_account: bool) {
     save_database_to_file();
 }
 
+pub fn edit_account(
+    user: &UserDTO,
+    target_email: &str,
+    new_email: Option<String>,
+    new_name: Option<String>,
+    new_pw: Option<String>,
+) {
+    trace!("edit_account");
+
+    if !block_on(access_control::auth(user, access_control::EDIT_ACCOUNT)) {
+        println!("{}", NOT_ALLOWED_MSG);
+        return;
+    }
+
+    //editing someone else's record additionally requires a teacher/admin role
+    let editing_self = target_email.eq(&user.email);
+    if !editing_self && !block_on(access_control::auth(user, access_control::EDIT_OTHER_ACCOUNT)) {
+        println!("{}", NOT_ALLOWED_MSG);
+        return;
+    }
+
+    let mut changed_fields = Vec::new();
+    let mut data = DATABASE.lock().unwrap();
+    for i in 0..(data.len()) {
+        let curr_user = &mut data[i];
+        if curr_user.email.eq(target_email) {
+            if let Some(email) = new_email {
+                curr_user.email = email;
+                changed_fields.push("email");
+            }
+            if let Some(name) = new_name {
+                curr_user.name = name;
+                changed_fields.push("name");
+            }
+            if let Some(pw) = new_pw {
+                match password_policy::check(&pw) {
+                    Ok(()) => {
+                        add_credential(curr_user, PASSWORD_CREDENTIAL, padded_hash(&pw));
+                        changed_fields.push("password");
+                    }
+                    Err(reason) => {
+                        println!("{}", reason);
+                        warn!("Rejected a weak password for {} via edit_account: {}", target_email, reason);
+                    }
+                }
+            }
+            break;
+        }
+    }
+
+    info!(
+        "{} edited account {} : fields changed = {:?}",
+        user.email, target_email, changed_fields
+    );
+
+    std::mem::drop(data);
+    save_database_to_file();
+}
+
+pub fn delete_account(user: &UserDTO, target_email: &str) {
+    trace!("delete_account");
+
+    if !block_on(access_control::auth(user, access_control::DELETE_ACCOUNT)) {
+        println!("{}", NOT_ALLOWED_MSG);
+        return;
+    }
+
+    let mut data = DATABASE.lock().unwrap();
+    let pos = match data.iter().position(|u| u.email.eq(target_email)) {
+        Some(pos) => pos,
+        None => {
+            println!("No Student found with that email");
+            warn!(
+                "{} tried to delete unknown account {}",
+                user.email, target_email
+            );
+            return;
+        }
+    };
+    let removed = data.remove(pos);
+    std::mem::drop(data);
+
+    //A teacher's id is granted access only through a "g, <id>, teacher" Casbin
+    //grouping line; drop it from the policy file here so it is not loaded again
+    //on the next start. The enforcer already running keeps it until then.
+    let teacher_line = format!("g, {}, teacher", removed.id);
+    if let Ok(policy) = std::fs::read_to_string(access_control::POLICY) {
+        let kept: Vec<&str> = policy.lines().collect();
+        let filtered: Vec<&str> = kept
+            .iter()
+            .copied()
+            .filter(|line| !line.trim().eq(&teacher_line))
+            .collect();
+        if filtered.len() != kept.len() {
+            std::fs::write(access_control::POLICY, filtered.join("\n")).unwrap();
+        }
+    }
+
+    info!("{} deleted account {}", user.email, target_email);
+    save_database_to_file();
+}
+
 pub fn reset_password(user: &UserDTO) {
     trace!("reset_password");
 
@@ -160,34 +411,116 @@ pub fn reset_password(user: &UserDTO) {
     println!("A token will been sent to the email");
     let mut rng = rand::thread_rng();
     let code = rng.gen_range(100000..999999);
+    let created_at = unix_now();
+
+    //create/overwrite the pending request for this email, keeping only the hash on disk
+    let mut requests = REQUESTS.lock().unwrap();
+    requests.retain(|r| !r.email.eq(&user.email));
+    requests.push(ResetRequest {
+        email: String::from(&user.email),
+        token_hash: padded_hash(&code.to_string()),
+        created_at,
+        consumed: false,
+    });
+    std::mem::drop(requests);
+    save_requests_to_file();
+
     send_password_mail(&user.email, &code.to_string());
     info!("{} asked for a password change", user.email);
 
     //if we find the email, ask for it
-    let code_entered = &input()
+    let code_entered: i32 = input()
         .inside(100000..999999)
         .msg("Please enter the token sent (6 numbers):\n")
         .get();
 
-    if code == *code_entered {
-        let pw = ask_for_pw(true);
+    let now = unix_now();
+
+    let mut requests = REQUESTS.lock().unwrap();
+    let request = requests.iter_mut().find(|r| r.email.eq(&user.email));
+    let verified = match request {
+        Some(r) if r.consumed => {
+            println!("This token has already been used");
+            false
+        }
+        Some(r) if now.saturating_sub(r.created_at) > 600 => {
+            println!("This token has expired, please request a new one");
+            false
+        }
+        Some(r) if verify(r.token_hash, &code_entered.to_string()) => {
+            r.consumed = true;
+            true
+        }
+        _ => {
+            println!("Wrong code");
+            false
+        }
+    };
+    std::mem::drop(requests);
+    save_requests_to_file();
+
+    if verified {
+        //only reveal the hint once mailbox/code possession is proven, never before
+        let data = DATABASE.lock().unwrap();
+        if let Some(curr_user) = data.iter().find(|u| u.id.eq(&user.id)) {
+            if let Some(hint) = &curr_user.password_hint {
+                println!("Password hint: {}", hint);
+            }
+        }
+        std::mem::drop(data);
+
+        let pw = ask_for_strong_pw(true);
         let mut data = DATABASE.lock().unwrap();
         for i in 0..(data.len()) {
-            let mut curr_user = &mut data[i];
+            let curr_user = &mut data[i];
             if curr_user.email.eq(&user.email) {
-                curr_user.pw_hash = padded_hash(&pw);
-                return;
+                add_credential(curr_user, PASSWORD_CREDENTIAL, padded_hash(&pw));
+                break;
             }
         }
+        std::mem::drop(data);
         info!("Succesfull password reset for {}", user.email);
     } else {
-        println!("Wrong code");
         warn!("Unsuccesfull password reset for {}", user.email)
     }
 
     save_database_to_file()
 }
 
+pub fn enroll_totp(user: &UserDTO) {
+    trace!("enroll_totp");
+
+    let secret = generate_secret();
+    let mut padded = [0u8; 128];
+    padded[..secret.len()].copy_from_slice(&secret);
+
+    let mut data = DATABASE.lock().unwrap();
+    let curr_user = match data.iter_mut().find(|u| u.id.eq(&user.id)) {
+        Some(curr_user) => curr_user,
+        None => {
+            //the hard-coded admin account has no DATABASE row and login never checks its TOTP
+            //credential, so there is nothing to enroll against; fail loudly instead of claiming success
+            println!("TOTP enrollment is not supported for this account");
+            warn!("{} attempted TOTP enrollment with no matching account", user.email);
+            return;
+        }
+    };
+    add_credential(curr_user, TOTP_CREDENTIAL, padded);
+    std::mem::drop(data);
+    save_database_to_file();
+
+    let secret_b32 = to_base32(&secret);
+    println!(
+        "Scan this secret into your authenticator app: {}",
+        secret_b32
+    );
+    println!(
+        "otpauth://totp/SEC_Projet:{}?secret={}&issuer=SEC_Projet",
+        user.email, secret_b32
+    );
+    info!("{} enrolled a TOTP second factor", user.email);
+}
+
 pub fn enter_grade(user: &UserDTO) {
     trace!("Enter_grade");
 
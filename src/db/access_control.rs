@@ -0,0 +1,40 @@
+use casbin::{CoreApi, Enforcer};
+use futures::executor::block_on;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+use super::UserDTO;
+
+pub const MODEL: &str = "access_control_model.conf";
+pub const POLICY: &str = "access_control_policy.csv";
+
+pub const TEACHER_ACC: &str = "create_teacher_account";
+pub const STUDENT_ACC: &str = "create_student_account";
+pub const ENTER_GRADE: &str = "enter_grade";
+pub const SHOW_GRADES: &str = "show_grades";
+pub const EDIT_ACCOUNT: &str = "edit_account";
+pub const EDIT_OTHER_ACCOUNT: &str = "edit_other_account";
+pub const DELETE_ACCOUNT: &str = "delete_account";
+
+lazy_static! {
+    static ref ENFORCER: Mutex<Enforcer> = {
+        let enforcer = block_on(Enforcer::new(MODEL, POLICY))
+            .expect("failed to load the access control model/policy");
+        Mutex::new(enforcer)
+    };
+}
+
+/// The hard-coded `admin` id is allowed every action, and every signed-in
+/// user is allowed to act on their own account (`EDIT_ACCOUNT`); any other
+/// action requires the caller's id to carry the matching Casbin role through
+/// a `g, <id>, <role>` grouping line in `POLICY`.
+pub async fn auth(user: &UserDTO, action: &str) -> bool {
+    if user.id.eq("admin") || action.eq(EDIT_ACCOUNT) {
+        return true;
+    }
+
+    let enforcer = ENFORCER.lock().unwrap();
+    enforcer
+        .enforce((user.id.as_str(), action))
+        .unwrap_or(false)
+}
@@ -0,0 +1,178 @@
+use read_input::prelude::*;
+use uuid::Uuid;
+
+use ldap3::LdapConn;
+use log::info;
+
+use super::hash::verify;
+use super::totp;
+use super::{
+    credential_of, save_database_to_file, unix_now, User, UserDTO, DATABASE, PASSWORD_CREDENTIAL,
+    TOTP_CREDENTIAL,
+};
+
+/// A source of truth that can turn an `(identifier, password)` pair into an
+/// authenticated `UserDTO`. `login` tries each configured backend in order
+/// and returns the first match.
+pub trait AuthBackend {
+    fn authenticate(&self, identifier: &str, password: &str) -> Option<UserDTO>;
+}
+
+/// The pre-existing behavior: look the identifier up in `DATABASE` (or the
+/// hard-coded admin account) and verify the stored hash.
+pub struct LocalBackend;
+
+impl AuthBackend for LocalBackend {
+    fn authenticate(&self, identifier: &str, password: &str) -> Option<UserDTO> {
+        if identifier.eq("admin") {
+            if verify(super::ADMIN_HASH, password) {
+                return Some(UserDTO {
+                    id: String::from("admin"),
+                    email: String::from("admin"),
+                });
+            }
+            return None;
+        }
+
+        //copy out everything needed and drop the lock before the (possibly slow,
+        //interactive) TOTP prompt, so other callers aren't blocked on DATABASE
+        //while this one user types their 6-digit code
+        let (dto, totp_secret) = {
+            let data = DATABASE.lock().unwrap();
+            let user = data.iter().find(|u| u.email.eq(identifier))?;
+            let password_cred = credential_of(user, PASSWORD_CREDENTIAL)?;
+            if !verify(password_cred.credential, password) {
+                return None;
+            }
+
+            let dto = UserDTO {
+                id: String::from(&user.id),
+                email: String::from(&user.email),
+            };
+            let totp_secret = credential_of(user, TOTP_CREDENTIAL).map(|c| c.credential);
+            (dto, totp_secret)
+        };
+
+        if let Some(secret) = totp_secret {
+            let entered: u32 = input()
+                .msg("Please enter the 6-digit code from your authenticator app:\n")
+                .get();
+            if !totp::verify(&secret[..totp::SECRET_LEN], unix_now(), entered) {
+                return None;
+            }
+        }
+
+        Some(dto)
+    }
+}
+
+/// Authenticates against an institutional directory by attempting a simple
+/// bind as `uid=<identifier>,ou=people,<base_dn>`. A successful bind
+/// auto-provisions a local `User` row with no `"password"` credential (it is
+/// authenticated externally) so the rest of the app can keep treating every
+/// authenticated user the same.
+pub struct LdapBackend {
+    pub host: String,
+    pub base_dn: String,
+}
+
+impl LdapBackend {
+    pub fn new(host: &str, base_dn: &str) -> Self {
+        LdapBackend {
+            host: String::from(host),
+            base_dn: String::from(base_dn),
+        }
+    }
+
+    fn bind_dn(&self, identifier: &str) -> String {
+        format!(
+            "uid={},ou=people,{}",
+            escape_rdn_value(identifier),
+            self.base_dn
+        )
+    }
+}
+
+/// Escapes an RFC 4514 RDN attribute value so a crafted `identifier` (e.g.
+/// one containing a comma) cannot inject extra RDN components and redirect
+/// the simple bind to a different DN than the intended `uid=...` entry.
+fn escape_rdn_value(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let last = chars.len().saturating_sub(1);
+    let mut escaped = String::with_capacity(value.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '#' if i == 0 => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' ' if i == 0 || i == last => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\0' => escaped.push_str("\\00"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+impl AuthBackend for LdapBackend {
+    fn authenticate(&self, identifier: &str, password: &str) -> Option<UserDTO> {
+        if password.is_empty() {
+            return None;
+        }
+
+        let mut conn = LdapConn::new(&self.host).ok()?;
+        conn.simple_bind(&self.bind_dn(identifier), password)
+            .ok()?
+            .success()
+            .ok()?;
+
+        let mut data = DATABASE.lock().unwrap();
+        if let Some(user) = data.iter().find(|u| u.email.eq(identifier)) {
+            return Some(UserDTO {
+                id: String::from(&user.id),
+                email: String::from(&user.email),
+            });
+        }
+
+        let id = Uuid::new_v4().to_string();
+        data.push(User {
+            id: String::from(&id),
+            email: String::from(identifier),
+            name: String::from(identifier),
+            credentials: Vec::new(),
+            grades: Vec::new(),
+            password_hint: None,
+        });
+        std::mem::drop(data);
+        save_database_to_file();
+        info!("Auto-provisioned externally-authenticated account for {}", identifier);
+
+        Some(UserDTO {
+            id,
+            email: String::from(identifier),
+        })
+    }
+}
+
+/// The ordered list of backends `login` consults. Local accounts are tried
+/// first so the existing fast path is untouched; the LDAP backend only
+/// joins the list when a directory server is configured via environment,
+/// letting institutions federate without every deployment needing one.
+pub fn configured_backends() -> Vec<Box<dyn AuthBackend>> {
+    let mut backends: Vec<Box<dyn AuthBackend>> = vec![Box::new(LocalBackend)];
+
+    if let (Ok(host), Ok(base_dn)) = (std::env::var("LDAP_HOST"), std::env::var("LDAP_BASE_DN")) {
+        backends.push(Box::new(LdapBackend::new(&host, &base_dn)));
+    }
+
+    backends
+}
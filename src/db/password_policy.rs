@@ -0,0 +1,44 @@
+const MIN_LENGTH: usize = 12;
+
+const COMMON_PASSWORDS: &[&str] = &[
+    "password",
+    "123456",
+    "123456789",
+    "qwerty",
+    "letmein",
+    "admin",
+    "welcome",
+    "password1",
+];
+
+/// Returns `Ok(())` when `pw` meets the minimum length, contains at least one
+/// uppercase, lowercase, digit and special character, and is not in the
+/// bundled common-password blocklist. Otherwise returns a short, user-facing
+/// reason so the caller can re-prompt.
+pub fn check(pw: &str) -> Result<(), String> {
+    if pw.chars().count() < MIN_LENGTH {
+        return Err(format!(
+            "Password must be at least {} characters long",
+            MIN_LENGTH
+        ));
+    }
+    if !pw.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(String::from("Password must contain an uppercase letter"));
+    }
+    if !pw.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err(String::from("Password must contain a lowercase letter"));
+    }
+    if !pw.chars().any(|c| c.is_ascii_digit()) {
+        return Err(String::from("Password must contain a digit"));
+    }
+    if !pw.chars().any(|c| !c.is_ascii_alphanumeric()) {
+        return Err(String::from("Password must contain a special character"));
+    }
+    if COMMON_PASSWORDS.contains(&pw.to_lowercase().as_str()) {
+        return Err(String::from(
+            "This password is too common, please choose another one",
+        ));
+    }
+
+    Ok(())
+}
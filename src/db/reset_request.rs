@@ -0,0 +1,46 @@
+use lazy_static::{__Deref, lazy_static};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::trace;
+
+use super::big_array::BigArray;
+
+pub const RESET_REQUESTS_FILE: &str = "password_reset_request.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResetRequest {
+    pub email: String,
+    #[serde(with = "BigArray")]
+    pub token_hash: [u8; 128],
+    pub created_at: u64,
+    pub consumed: bool,
+}
+
+lazy_static! {
+    pub static ref REQUESTS: Mutex<Vec<ResetRequest>> = {
+        let data = read_requests_from_file(RESET_REQUESTS_FILE).unwrap_or(Vec::new());
+        Mutex::new(data)
+    };
+}
+
+pub fn read_requests_from_file<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<ResetRequest>, Box<dyn Error>> {
+    trace!("Read_reset_requests");
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let data = serde_json::from_reader(reader)?;
+    Ok(data)
+}
+
+pub fn save_requests_to_file() {
+    trace!("Save_reset_requests");
+    let file = File::create(RESET_REQUESTS_FILE).unwrap();
+    let writer = BufWriter::new(file);
+    serde_json::to_writer(writer, REQUESTS.lock().unwrap().deref()).unwrap();
+}
@@ -0,0 +1,43 @@
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+
+pub const SECRET_LEN: usize = 20;
+const STEP_SECONDS: u64 = 30;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Generates a random 20-byte TOTP secret for a new enrollment.
+pub fn generate_secret() -> [u8; SECRET_LEN] {
+    let mut secret = [0u8; SECRET_LEN];
+    rand::thread_rng().fill(&mut secret);
+    secret
+}
+
+/// Encodes a secret the way an authenticator app expects it to be entered.
+pub fn to_base32(secret: &[u8]) -> String {
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, secret)
+}
+
+/// RFC 6238: HMAC-SHA1 over the 8-byte big-endian time-step counter,
+/// dynamically truncated per RFC 4226 and reduced to 6 digits.
+fn code_at_step(secret: &[u8], step: u64) -> u32 {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts a key of any size");
+    mac.update(&step.to_be_bytes());
+    let result = mac.finalize().into_bytes();
+
+    let offset = (result[result.len() - 1] & 0x0f) as usize;
+    let truncated = ((result[offset] as u32 & 0x7f) << 24)
+        | ((result[offset + 1] as u32) << 16)
+        | ((result[offset + 2] as u32) << 8)
+        | (result[offset + 3] as u32);
+    truncated % 1_000_000
+}
+
+/// Accepts the current 30-second step plus one step of clock skew either way.
+pub fn verify(secret: &[u8], now: u64, code: u32) -> bool {
+    let step = now / STEP_SECONDS;
+    [step.saturating_sub(1), step, step + 1]
+        .iter()
+        .any(|&t| code_at_step(secret, t) == code)
+}